@@ -0,0 +1,286 @@
+use std::io::{self, Read};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use {BatteryInfo, ChargingState, PowerBackend, PowerError};
+
+const POWER_PATH: &'static str = "/sys/class/power_supply";
+
+/// The Linux backend, which enumerates batteries under
+/// `/sys/class/power_supply` and reads their sysfs attributes.
+pub struct LinuxBackend;
+
+impl PowerBackend for LinuxBackend {
+    fn batteries(&self) -> Result<Vec<Box<dyn BatteryInfo>>, PowerError> {
+        let mut batteries: Vec<Box<dyn BatteryInfo>> = Vec::new();
+        let entries = try!(fs::read_dir(POWER_PATH).map_err(|e| PowerError::Io {
+            path: PathBuf::from(POWER_PATH),
+            source: e,
+        }));
+        for entry in entries {
+            let entry = try!(entry.map_err(|e| PowerError::Io {
+                path: PathBuf::from(POWER_PATH),
+                source: e,
+            }));
+            let ps = PowerSupply::new(entry.path());
+            if try!(ps.is_battery()) {
+                // Snapshot each battery once so every reported quantity comes
+                // from a single consistent `uevent` read.
+                batteries.push(Box::new(try!(ps.snapshot())));
+            }
+        }
+        Ok(batteries)
+    }
+}
+
+/// A source of raw `power_supply` properties, keyed by the sysfs attribute
+/// name (e.g. `energy_now`, `status`). Implemented both by a live
+/// `PowerSupply` that reads individual files and by a `Snapshot` parsed from
+/// a single `uevent` read.
+///
+/// All the derived quantities (`percent`, `status`, `energy_now`, …) are
+/// default methods here, so they work identically against either source.
+trait PropSource {
+    /// The raw, untrimmed string value of a property, or `None` if the
+    /// source does not expose it.
+    fn raw_prop(&self, prop_name: &str) -> Result<Option<String>, PowerError>;
+
+    fn has_prop(&self, prop_name: &str) -> bool {
+        self.raw_prop(prop_name).map(|v| v.is_some()).unwrap_or(false)
+    }
+
+    fn read_prop<T>(&self, prop_name: &str) -> Result<T, PowerError>
+        where T: FromStr,
+    {
+        match try!(self.raw_prop(prop_name)) {
+            Some(prop) => {
+                let trimmed = prop.trim_right();
+                trimmed.parse::<T>().map_err(|_| PowerError::Parse {
+                    prop: prop_name.to_string(),
+                    value: trimmed.to_string(),
+                })
+            }
+            None => Err(PowerError::MissingProp(prop_name.to_string())),
+        }
+    }
+
+    fn percent(&self) -> Result<i8, PowerError> {
+        if self.has_prop("capacity") {
+            self.read_prop::<i8>("capacity")
+        } else {
+            let ratio = try!(self.energy_now()) / try!(self.energy_full());
+            Ok((ratio * 100f64) as i8)
+        }
+    }
+
+    fn status(&self) -> Result<ChargingState, PowerError> {
+        self.read_prop::<ChargingState>("status")
+    }
+
+    /// Current charge expressed as energy in µWh.
+    ///
+    /// Prefers `energy_now`; on drivers that only report capacity in µAh
+    /// (the common ACPI case) it falls back to `charge_now * voltage_now`.
+    fn energy_now(&self) -> Result<f64, PowerError> {
+        if self.has_prop("energy_now") {
+            self.read_prop::<f64>("energy_now")
+        } else {
+            let charge = try!(self.read_prop::<f64>("charge_now")); // µAh
+            let voltage = try!(self.read_prop::<f64>("voltage_now")); // µV
+            Ok(charge * voltage / 1_000_000f64) // µAh·µV → µWh
+        }
+    }
+
+    /// Full charge expressed as energy in µWh, from `energy_full` or
+    /// `charge_full * voltage_now`.
+    fn energy_full(&self) -> Result<f64, PowerError> {
+        if self.has_prop("energy_full") {
+            self.read_prop::<f64>("energy_full")
+        } else {
+            let charge = try!(self.read_prop::<f64>("charge_full")); // µAh
+            let voltage = try!(self.read_prop::<f64>("voltage_now")); // µV
+            Ok(charge * voltage / 1_000_000f64) // µAh·µV → µWh
+        }
+    }
+
+    /// Full charge as originally manufactured, in µWh, from
+    /// `energy_full_design` or `charge_full_design * voltage_now`.
+    fn full_design(&self) -> Result<f64, PowerError> {
+        if self.has_prop("energy_full_design") {
+            self.read_prop::<f64>("energy_full_design")
+        } else {
+            let charge = try!(self.read_prop::<f64>("charge_full_design")); // µAh
+            let voltage = try!(self.read_prop::<f64>("voltage_now")); // µV
+            Ok(charge * voltage / 1_000_000f64) // µAh·µV → µWh
+        }
+    }
+
+    fn has_design_capacity(&self) -> bool {
+        self.has_prop("energy_full_design") || self.has_prop("charge_full_design")
+    }
+
+    /// Full-charge capacity as a percentage of the design capacity; a battery
+    /// with no wear reports 100.
+    fn health(&self) -> Result<f64, PowerError> {
+        Ok(try!(self.energy_full()) / try!(self.full_design()) * 100f64)
+    }
+
+    /// Number of charge cycles, when the driver exposes it.
+    fn cycle_count(&self) -> Result<Option<u32>, PowerError> {
+        if self.has_prop("cycle_count") {
+            Ok(Some(try!(self.read_prop::<u32>("cycle_count"))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Instantaneous draw expressed as power in µW, from `power_now` or
+    /// `current_now * voltage_now`.
+    fn power_now(&self) -> Result<f64, PowerError> {
+        if self.has_prop("power_now") {
+            self.read_prop::<f64>("power_now")
+        } else {
+            let current = try!(self.read_prop::<f64>("current_now")); // µA
+            let voltage = try!(self.read_prop::<f64>("voltage_now")); // µV
+            Ok(current * voltage / 1_000_000f64) // µA·µV → µW
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PowerSupply {
+    base_path: PathBuf,
+}
+
+impl PowerSupply {
+    fn new(path: PathBuf) -> PowerSupply {
+        PowerSupply { base_path: path }
+    }
+
+    fn name(&self) -> &str {
+        self.base_path.file_name().unwrap().to_str().unwrap()
+    }
+
+    fn is_battery(&self) -> Result<bool, PowerError> {
+        Ok(try!(self.read_prop::<String>("type")) == "Battery")
+    }
+
+    /// Read and parse the battery's `uevent` file in one go, returning the
+    /// `POWER_SUPPLY_*` keys mapped to their raw values. Each access through
+    /// a `Snapshot` built from this map (see `snapshot`) then comes from this
+    /// single, consistent read instead of reopening a file per property.
+    fn read_uevent(&self) -> Result<HashMap<String, String>, PowerError> {
+        let path = self.base_path.join("uevent");
+        let mut contents = String::new();
+        let mut f = try!(File::open(&path).map_err(|e| PowerError::Io {
+            path: path.clone(),
+            source: e,
+        }));
+        try!(f.read_to_string(&mut contents).map_err(|e| PowerError::Io {
+            path: path.clone(),
+            source: e,
+        }));
+
+        let mut map = HashMap::new();
+        for line in contents.lines() {
+            if let Some(idx) = line.find('=') {
+                let (key, value) = line.split_at(idx);
+                map.insert(key.to_string(), value[1..].to_string());
+            }
+        }
+        Ok(map)
+    }
+
+    /// Take a consistent snapshot of every property from a single `uevent`
+    /// read, avoiding torn reads where, say, capacity and power come from
+    /// different instants.
+    fn snapshot(&self) -> Result<Snapshot, PowerError> {
+        Ok(Snapshot {
+            name: self.name().to_string(),
+            props: try!(self.read_uevent()),
+        })
+    }
+}
+
+impl PropSource for PowerSupply {
+    fn raw_prop(&self, prop_name: &str) -> Result<Option<String>, PowerError> {
+        let prop_path = self.base_path.join(prop_name);
+        match File::open(&prop_path) {
+            Ok(mut f) => {
+                let mut prop = String::new();
+                try!(f.read_to_string(&mut prop).map_err(|e| PowerError::Io {
+                    path: prop_path.clone(),
+                    source: e,
+                }));
+                Ok(Some(prop))
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(PowerError::Io { path: prop_path, source: e }),
+        }
+    }
+}
+
+/// A consistent, parsed view of a battery's properties taken from one
+/// `uevent` read. Every derived quantity a caller asks for comes from this
+/// single snapshot, so capacity and power can never come from different
+/// instants. Shares the derived accessors with `PowerSupply` through
+/// `PropSource`.
+struct Snapshot {
+    name: String,
+    props: HashMap<String, String>,
+}
+
+impl PropSource for Snapshot {
+    fn raw_prop(&self, prop_name: &str) -> Result<Option<String>, PowerError> {
+        let key = format!("POWER_SUPPLY_{}", prop_name.to_uppercase());
+        Ok(self.props.get(&key).cloned())
+    }
+}
+
+impl Snapshot {
+    /// Tag an error with this battery's name, so callers get messages like
+    /// "failed to parse 'capacity' value '10x' for BAT0".
+    fn labeled(&self, source: PowerError) -> PowerError {
+        PowerError::Battery { name: self.name.clone(), source: Box::new(source) }
+    }
+}
+
+impl BatteryInfo for Snapshot {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn percent(&self) -> Result<i8, PowerError> {
+        PropSource::percent(self).map_err(|e| self.labeled(e))
+    }
+
+    fn status(&self) -> Result<ChargingState, PowerError> {
+        PropSource::status(self).map_err(|e| self.labeled(e))
+    }
+
+    fn energy_now(&self) -> Result<f64, PowerError> {
+        PropSource::energy_now(self).map_err(|e| self.labeled(e))
+    }
+
+    fn power_now(&self) -> Result<f64, PowerError> {
+        PropSource::power_now(self).map_err(|e| self.labeled(e))
+    }
+
+    fn energy_full(&self) -> Result<f64, PowerError> {
+        PropSource::energy_full(self).map_err(|e| self.labeled(e))
+    }
+
+    fn health(&self) -> Result<Option<f64>, PowerError> {
+        if self.has_design_capacity() {
+            Ok(Some(try!(PropSource::health(self).map_err(|e| self.labeled(e)))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn cycle_count(&self) -> Result<Option<u32>, PowerError> {
+        PropSource::cycle_count(self).map_err(|e| self.labeled(e))
+    }
+}
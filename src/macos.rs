@@ -0,0 +1,12 @@
+use {BatteryInfo, PowerBackend, PowerError};
+
+/// The macOS backend. It is a stub: it does not query IOKit's
+/// `IOPowerSources` API and reports no batteries.
+pub struct MacosBackend;
+
+impl PowerBackend for MacosBackend {
+    fn batteries(&self) -> Result<Vec<Box<dyn BatteryInfo>>, PowerError> {
+        // TODO: enumerate via IOPSCopyPowerSourcesInfo / IOPSGetPowerSourceDescription.
+        Ok(Vec::new())
+    }
+}
@@ -1,124 +1,284 @@
-use std::io::{self, Read};
-use std::fs::{self, File};
+use std::io;
+use std::env;
+use std::thread;
 use std::path::PathBuf;
 use std::time::Duration;
-use std::str::FromStr;
 use std::fmt;
-use std::num;
 use std::string::ParseError;
 use std::error::Error;
 
-const POWER_PATH: &'static str = "/sys/class/power_supply";
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+#[cfg(target_os = "linux")]
+fn backend() -> Box<dyn PowerBackend> {
+    Box::new(linux::LinuxBackend)
+}
+
+#[cfg(target_os = "macos")]
+fn backend() -> Box<dyn PowerBackend> {
+    Box::new(macos::MacosBackend)
+}
+
+#[cfg(target_os = "windows")]
+fn backend() -> Box<dyn PowerBackend> {
+    Box::new(windows::WindowsBackend)
+}
 
 fn main() {
-    let batteries: Vec<PowerSupply> =
-        fs::read_dir(POWER_PATH).expect("can't list batteries")
-        .map(|entry| PowerSupply::new(entry.unwrap().path()))
-        .filter(|ps| ps.is_battery().expect("can't list batteries"))
-        .collect();
+    if let Err(e) = run() {
+        eprintln!("powar: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), PowerError> {
+    let opts = try!(Options::from_args(env::args().skip(1)));
+    let backend = backend();
+
+    if opts.watch {
+        loop {
+            let batteries = try!(backend.batteries());
+            try!(report(&batteries));
+            // There is no uevent/inotify watch: state is re-read and
+            // reprinted on a timed poll every `--interval`.
+            thread::sleep(opts.interval);
+        }
+    } else {
+        let batteries = try!(backend.batteries());
+        try!(report(&batteries));
+        Ok(())
+    }
+}
+
+/// Command line options controlling how the tool runs.
+struct Options {
+    /// Keep running and reprint every `interval` instead of printing once.
+    /// Kernel change notifications are not watched.
+    watch: bool,
+    /// Time between reprints in `--watch` mode.
+    interval: Duration,
+}
+
+impl Options {
+    fn from_args<I: Iterator<Item = String>>(args: I) -> Result<Options, PowerError> {
+        let mut opts = Options { watch: false, interval: Duration::from_secs(2) };
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--watch" => opts.watch = true,
+                "--interval" => {
+                    let raw = try!(args.next().ok_or_else(||
+                        PowerError::Usage("--interval requires a number of seconds".to_string())));
+                    let secs = try!(raw.parse::<u64>().map_err(|_|
+                        PowerError::Usage(format!("invalid --interval value '{}'", raw))));
+                    opts.interval = Duration::from_secs(secs);
+                }
+                other => return Err(PowerError::Usage(format!("unknown argument: {}", other))),
+            }
+        }
+        Ok(opts)
+    }
+}
 
+/// Print the current state of every battery and the aggregate runtime
+/// estimate once.
+fn report(batteries: &[Box<dyn BatteryInfo>]) -> Result<(), PowerError> {
     for bat in batteries.iter() {
         println!("{}: {}% ({})",
                  bat.name(),
-                 bat.percent().expect("Could not read battery"),
-                 bat.status().expect("Could not read battery"));
+                 try!(bat.percent()),
+                 try!(bat.status()));
+        if let Some(health) = try!(bat.health()) {
+            match try!(bat.cycle_count()) {
+                Some(cycles) =>
+                    println!("{}: {:.0}% health ({} cycles)", bat.name(), health, cycles),
+                None =>
+                    println!("{}: {:.0}% health", bat.name(), health),
+            }
+        }
     }
-    let runtime = format_time(combined_runtime(&batteries));
-    println!("Estimated runtime (all batteries): {}", runtime);
+    match try!(combined_runtime(batteries)) {
+        Runtime::ToEmpty(d) =>
+            println!("Estimated runtime (all batteries): {} until empty", format_time(d)),
+        Runtime::ToFull(d) =>
+            println!("Estimated runtime (all batteries): {} until full", format_time(d)),
+        Runtime::Idle =>
+            println!("Estimated runtime (all batteries): idle"),
+    }
+    Ok(())
 }
 
-#[derive(Debug)]
-struct PowerError;
+/// A platform-specific way of enumerating the machine's batteries. Each OS
+/// backend implements this so `main` never touches platform details. The
+/// Linux backend reads sysfs; the macOS and Windows backends report no
+/// batteries.
+trait PowerBackend {
+    fn batteries(&self) -> Result<Vec<Box<dyn BatteryInfo>>, PowerError>;
+}
 
-impl Error for PowerError {
-    fn description(&self) -> &str {
-        "PowerError"
+/// A single battery, independent of how its values are obtained. This is the
+/// only surface `main` and `combined_runtime` see.
+trait BatteryInfo {
+    fn name(&self) -> &str;
+    fn percent(&self) -> Result<i8, PowerError>;
+    fn status(&self) -> Result<ChargingState, PowerError>;
+    fn energy_now(&self) -> Result<f64, PowerError>;
+    fn power_now(&self) -> Result<f64, PowerError>;
+    fn energy_full(&self) -> Result<f64, PowerError>;
+
+    /// Full-charge capacity as a percentage of design capacity, when the
+    /// backend can determine it.
+    fn health(&self) -> Result<Option<f64>, PowerError> {
+        Ok(None)
     }
 
-    fn cause(&self) -> Option<&Error> {
-        None
+    /// Charge cycle count, when the backend exposes it.
+    fn cycle_count(&self) -> Result<Option<u32>, PowerError> {
+        Ok(None)
     }
 }
 
-impl fmt::Display for PowerError {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(formatter, "{}", self.description())
-    }
+/// The runtime estimate for the aggregate battery pack, whose meaning
+/// depends on whether the pack is charging or discharging.
+#[derive(Debug, PartialEq, Eq)]
+enum Runtime {
+    /// Time remaining before the batteries are empty (discharging).
+    ToEmpty(Duration),
+    /// Time remaining before the batteries are full (charging).
+    ToFull(Duration),
+    /// Fully charged, or no meaningful estimate (zero draw).
+    Idle,
 }
 
-impl From<num::ParseFloatError> for PowerError {
-    fn from(_: num::ParseFloatError) -> PowerError {
-        PowerError
-    }
+/// The things that can go wrong while reading a battery, each carrying
+/// enough context to say *which* file or property failed.
+#[derive(Debug)]
+enum PowerError {
+    /// A sysfs file could not be opened or read.
+    Io { path: PathBuf, source: io::Error },
+    /// A property's contents could not be parsed into the expected type.
+    Parse { prop: String, value: String },
+    /// A property the driver was expected to expose is absent.
+    MissingProp(String),
+    /// A command line argument was missing or malformed.
+    Usage(String),
+    /// A failure reading a specific battery, naming which one.
+    Battery { name: String, source: Box<PowerError> },
 }
 
-impl From<num::ParseIntError> for PowerError {
-    fn from(_: num::ParseIntError) -> PowerError {
-        PowerError
+impl Error for PowerError {
+    fn description(&self) -> &str {
+        "power error"
     }
-}
 
-impl From<ParseError> for PowerError {
-    fn from(_: ParseError) -> PowerError {
-        PowerError
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            PowerError::Io { ref source, .. } => Some(source),
+            PowerError::Battery { ref source, .. } => Some(&**source),
+            _ => None,
+        }
     }
 }
 
-impl From<io::Error> for PowerError {
-    fn from(_: io::Error) -> PowerError {
-        PowerError
+impl fmt::Display for PowerError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            PowerError::Io { ref path, ref source } =>
+                write!(formatter, "failed to read {}: {}", path.display(), source),
+            PowerError::Parse { ref prop, ref value } =>
+                write!(formatter, "failed to parse '{}' value '{}'", prop, value),
+            PowerError::MissingProp(ref prop) =>
+                write!(formatter, "missing property '{}'", prop),
+            PowerError::Usage(ref msg) =>
+                write!(formatter, "{}", msg),
+            PowerError::Battery { ref name, ref source } =>
+                write!(formatter, "{} for {}", source, name),
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct PowerSupply {
-    base_path: PathBuf,
+/// The charging state reported by the kernel in `status`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum ChargingState {
+    Charging,
+    Discharging,
+    Full,
+    NotCharging,
+    Unknown,
 }
 
-impl PowerSupply {
-    fn new(path: PathBuf) -> PowerSupply {
-        PowerSupply { base_path: path }
-    }
+impl std::str::FromStr for ChargingState {
+    type Err = ParseError;
 
-    fn read_prop<T>(&self, prop_name: &str) -> Result<T, PowerError>
-        where T: FromStr,
-              <T as FromStr>::Err: fmt::Debug,
-              PowerError: From<<T as FromStr>::Err>,
-    {
-        let type_path = self.base_path.join(prop_name);
-        let mut prop = String::new();
-        let mut f = try!(File::open(type_path));
-        try!(f.read_to_string(&mut prop));
-        Ok(try!(prop.trim_right().to_string().parse::<T>()))
+    fn from_str(s: &str) -> Result<ChargingState, ParseError> {
+        Ok(match s {
+            "Charging" => ChargingState::Charging,
+            "Discharging" => ChargingState::Discharging,
+            "Full" => ChargingState::Full,
+            "Not charging" => ChargingState::NotCharging,
+            _ => ChargingState::Unknown,
+        })
     }
+}
 
-    fn name(&self) -> &str {
-        self.base_path.file_name().unwrap().to_str().unwrap()
+impl fmt::Display for ChargingState {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let s = match *self {
+            ChargingState::Charging => "Charging",
+            ChargingState::Discharging => "Discharging",
+            ChargingState::Full => "Full",
+            ChargingState::NotCharging => "Not charging",
+            ChargingState::Unknown => "Unknown",
+        };
+        write!(formatter, "{}", s)
     }
+}
 
-    fn is_battery(&self) -> Result<bool, PowerError> {
-        Ok(try!(self.read_prop::<String>("type")) == "Battery")
+fn combined_runtime(batteries: &[Box<dyn BatteryInfo>]) -> Result<Runtime, PowerError> {
+    let mut total_energy = 0f64; // µW*h
+    let mut total_power = 0f64; // µW*h/h
+    let mut charging = false;
+    let mut all_full = true;
+
+    for bat in batteries.iter() {
+        total_energy += try!(bat.energy_now());
+        total_power += try!(bat.power_now());
+        let state = try!(bat.status());
+        if state == ChargingState::Charging {
+            charging = true;
+        }
+        if state != ChargingState::Full {
+            all_full = false;
+        }
     }
 
-    fn percent(&self) -> Result<i8, PowerError> {
-        self.read_prop::<i8>("capacity")
+    if total_power == 0f64 || all_full {
+        return Ok(Runtime::Idle);
     }
 
-    fn status(&self) -> Result<String, PowerError> {
-        self.read_prop::<String>("status")
+    if charging {
+        // `energy_full` is only needed here; reading it while discharging
+        // would abort the estimate on drivers that omit the *_full files.
+        let mut total_full = 0f64; // µW*h
+        for bat in batteries.iter() {
+            total_full += try!(bat.energy_full());
+        }
+        let remaining = (total_full - total_energy) / total_power; // hours
+        Ok(Runtime::ToFull(hours_to_duration(remaining)))
+    } else {
+        let remaining = total_energy / total_power; // hours
+        Ok(Runtime::ToEmpty(hours_to_duration(remaining)))
     }
 }
 
-fn combined_runtime(batteries: &[PowerSupply]) -> Duration {
-    let total_energy = batteries.iter()
-        .map(|b| b.read_prop::<f64>("energy_now").expect("Could not read battery"))
-        .fold(0f64, |a, b| a + b); // µW*h
-    let total_power = batteries.iter()
-        .map(|b| b.read_prop::<f64>("power_now").expect("Could not read battery"))
-        .fold(0f64, |a, b| a + b) as f64; // µW*h/h
-    let runtime = total_energy / total_power; // hours
-    let runtime_ms = runtime * 60f64 * 60f64 * 1000f64;
-    Duration::from_millis(runtime_ms as u64)
+fn hours_to_duration(hours: f64) -> Duration {
+    let ms = hours * 60f64 * 60f64 * 1000f64;
+    Duration::from_millis(ms as u64)
 }
 
 fn format_time(d: Duration) -> String {
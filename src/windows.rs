@@ -0,0 +1,12 @@
+use {BatteryInfo, PowerBackend, PowerError};
+
+/// The Windows backend. It is a stub: it does not call
+/// `GetSystemPowerStatus` and reports no batteries.
+pub struct WindowsBackend;
+
+impl PowerBackend for WindowsBackend {
+    fn batteries(&self) -> Result<Vec<Box<dyn BatteryInfo>>, PowerError> {
+        // TODO: query GetSystemPowerStatus and expose the single system battery.
+        Ok(Vec::new())
+    }
+}